@@ -0,0 +1,163 @@
+//! Optional outbound mirroring of starboard posts to Discord webhooks.
+//! Entirely opt-in: nothing in this module is reachable unless the `bridge`
+//! cargo feature is enabled, so users who don't configure a bridge pay no
+//! runtime or compile cost.
+use serde::{Deserialize, Serialize};
+use serenity::{client::Context, model::id::GuildId};
+
+use crate::WatchedMessage;
+
+/// Where a promoted starboard post gets mirrored to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum BridgeTarget {
+    Webhook { url: String },
+}
+
+/// Whether `url` points at a genuine Discord webhook endpoint. The bridge
+/// only ever fires an HTTP POST at `BridgeTarget::Webhook` urls, so without
+/// this an admin (or a compromised admin account) could point it at an
+/// arbitrary internal or external host — a straightforward SSRF. Only
+/// `https://discord(app).com/api/webhooks/...` is accepted.
+pub fn is_discord_webhook_url(url: &str) -> bool {
+    let parsed = match reqwest::Url::parse(url) {
+        Ok(parsed) => parsed,
+        Err(_) => return false,
+    };
+    parsed.scheme() == "https"
+        && matches!(parsed.host_str(), Some("discord.com") | Some("discordapp.com"))
+        && parsed.path().starts_with("/api/webhooks/")
+}
+
+/// Discord rejects any message (webhook posts included) whose content
+/// exceeds this many characters.
+const DISCORD_CONTENT_LIMIT: usize = 2000;
+
+/// Formats a plain-text summary of a promoted message and relays it to
+/// every bridge target configured for `guild_id`, splitting it into
+/// multiple posts if it's too long for a single one.
+pub async fn mirror_to_bridges(
+    ctx: &Context,
+    guild_id: &GuildId,
+    watched_message: &WatchedMessage,
+    targets: &[BridgeTarget],
+) {
+    let summary = format_summary(guild_id, watched_message);
+    let chunks = chunk_content(&summary, DISCORD_CONTENT_LIMIT);
+    for target in targets {
+        match target {
+            BridgeTarget::Webhook { url } => {
+                for chunk in &chunks {
+                    if let Err(err) = send_to_webhook(ctx, url, chunk).await {
+                        eprintln!("Error mirroring starboard post to webhook: {}", err);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Splits `content` into pieces of at most `limit` chars, breaking on char
+/// boundaries so multi-byte UTF-8 sequences are never cut in half.
+fn chunk_content(content: &str, limit: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for ch in content.chars() {
+        if current.chars().count() >= limit {
+            chunks.push(std::mem::take(&mut current));
+        }
+        current.push(ch);
+    }
+    if !current.is_empty() || chunks.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+fn format_summary(guild_id: &GuildId, watched_message: &WatchedMessage) -> String {
+    let author = &watched_message.message.author.name;
+    let content = &watched_message.message.content;
+    let jump_url = watched_message.url(guild_id);
+    let attachments = watched_message
+        .message
+        .attachments
+        .iter()
+        .map(|a| a.url.clone())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let mut summary = format!("\u{2b50} {}: {}", author, content);
+    if !attachments.is_empty() {
+        summary.push(' ');
+        summary.push_str(&attachments);
+    }
+    summary.push(' ');
+    summary.push_str(&jump_url);
+    summary
+}
+
+async fn send_to_webhook(_ctx: &Context, url: &str, content: &str) -> Result<(), String> {
+    // Belt-and-suspenders: the slash command handler already validates this
+    // before persisting a `BridgeTarget::Webhook`, but a bad URL reaching
+    // this far (e.g. from a future config migration) must not turn into an
+    // SSRF, so re-check at the point the request actually goes out.
+    if !is_discord_webhook_url(url) {
+        return Err(format!("Refusing to POST to non-Discord webhook url `{}`", url));
+    }
+    reqwest::Client::new()
+        .post(url)
+        .json(&serde_json::json!({ "content": content }))
+        .send()
+        .await
+        .map_err(|err| format!("Could not send webhook: {}", err))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_genuine_discord_webhook_urls() {
+        assert!(is_discord_webhook_url("https://discord.com/api/webhooks/1/abc"));
+        assert!(is_discord_webhook_url("https://discordapp.com/api/webhooks/1/abc"));
+    }
+
+    #[test]
+    fn rejects_non_discord_hosts() {
+        assert!(!is_discord_webhook_url("https://evil.com/api/webhooks/1/abc"));
+        assert!(!is_discord_webhook_url("https://discord.com.evil.com/api/webhooks/1/abc"));
+        assert!(!is_discord_webhook_url("http://169.254.169.254/latest/meta-data/"));
+    }
+
+    #[test]
+    fn rejects_non_webhook_paths_and_schemes() {
+        assert!(!is_discord_webhook_url("https://discord.com/api/users/@me"));
+        assert!(!is_discord_webhook_url("http://discord.com/api/webhooks/1/abc"));
+        assert!(!is_discord_webhook_url("not a url"));
+    }
+
+    #[test]
+    fn short_content_is_left_as_a_single_chunk() {
+        assert_eq!(chunk_content("hello", 2000), vec!["hello".to_string()]);
+    }
+
+    #[test]
+    fn long_content_is_split_into_limit_sized_chunks() {
+        let content = "a".repeat(2500);
+        let chunks = chunk_content(&content, 2000);
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].chars().count(), 2000);
+        assert_eq!(chunks[1].chars().count(), 500);
+        assert_eq!(chunks.concat(), content);
+    }
+
+    #[test]
+    fn chunking_never_splits_a_multi_byte_character() {
+        let content = "aaa\u{2b50}\u{2b50}\u{2b50}";
+        let chunks = chunk_content(content, 3);
+
+        assert_eq!(chunks, vec!["aaa".to_string(), "\u{2b50}\u{2b50}\u{2b50}".to_string()]);
+        assert_eq!(chunks.concat(), content);
+    }
+}
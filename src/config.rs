@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use serenity::model::id::{ChannelId, EmojiId};
+
+#[cfg(feature = "bridge")]
+use crate::bridge::BridgeTarget;
+
+/// Per-guild overrides for the starboard. Any field left `None` falls back
+/// to the bot-wide defaults the process was started with, so a server that
+/// hasn't run `/starboard` yet behaves exactly like before this existed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GuildConfig {
+    pub threshold: Option<usize>,
+    pub color: Option<u32>,
+    pub starboard_channel: Option<ChannelId>,
+    pub star_emoji: Option<EmojiId>,
+    pub admin_star_emoji: Option<EmojiId>,
+    #[cfg(feature = "bridge")]
+    pub bridge_targets: Vec<BridgeTarget>,
+}
+
+/// The defaults a guild falls back to when it hasn't overridden a setting:
+/// the starboard channel and star emoji ids from the bot's startup
+/// environment, plus a fixed threshold and color.
+pub struct Defaults {
+    pub threshold: usize,
+    pub color: u32,
+    pub starboard_channel: ChannelId,
+    pub star_emoji: EmojiId,
+    pub admin_star_emoji: EmojiId,
+}
+
+/// A guild's fully resolved settings: its `GuildConfig` overrides merged
+/// with [`Defaults`].
+pub struct ResolvedConfig {
+    pub threshold: usize,
+    pub color: u32,
+    pub starboard_channel: ChannelId,
+    pub star_emoji: EmojiId,
+    pub admin_star_emoji: EmojiId,
+}
+
+impl GuildConfig {
+    pub fn resolve(&self, defaults: &Defaults) -> ResolvedConfig {
+        ResolvedConfig {
+            threshold: self.threshold.unwrap_or(defaults.threshold),
+            color: self.color.unwrap_or(defaults.color),
+            starboard_channel: self.starboard_channel.unwrap_or(defaults.starboard_channel),
+            star_emoji: self.star_emoji.unwrap_or(defaults.star_emoji),
+            admin_star_emoji: self.admin_star_emoji.unwrap_or(defaults.admin_star_emoji),
+        }
+    }
+}
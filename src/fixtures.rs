@@ -0,0 +1,40 @@
+//! Test-only helpers for building serenity model values, which can't be
+//! constructed directly (most are `#[non_exhaustive]` and have no public
+//! constructor) but deserialize fine from a minimal JSON payload.
+use serenity::model::channel::Message;
+
+pub(crate) fn message(id: u64, author_id: u64, author_name: &str) -> Message {
+    serde_json::from_value(serde_json::json!({
+        "id": id.to_string(),
+        "attachments": [],
+        "author": {
+            "id": author_id.to_string(),
+            "avatar": null,
+            "bot": false,
+            "discriminator": "0001",
+            "username": author_name,
+            "public_flags": null,
+        },
+        "channel_id": "100",
+        "content": "starred content",
+        "edited_timestamp": null,
+        "embeds": [],
+        "guild_id": null,
+        "type": 0,
+        "member": null,
+        "mention_everyone": false,
+        "mention_roles": [],
+        "mentions": [],
+        "pinned": false,
+        "timestamp": "2021-01-01T00:00:00.000000+00:00",
+        "tts": false,
+        "webhook_id": null,
+        "activity": null,
+        "application": null,
+        "message_reference": null,
+        "flags": null,
+        "referenced_message": null,
+        "interaction": null,
+    }))
+    .expect("fixture message should deserialize")
+}
@@ -0,0 +1,156 @@
+use std::future::Future;
+
+use serenity::{
+    model::id::{ChannelId, GuildId, RoleId, UserId},
+    prelude::Context,
+};
+
+/// Resolves Discord mention/emoji tokens (`<@id>`, `<#id>`, `<@&id>`,
+/// `<:name:id>`) in `content` into human-readable text for display in a
+/// starboard embed, where raw tokens don't render at all. `@everyone` and
+/// `@here` are neutralized with a zero-width space so copying the embed
+/// text elsewhere can never re-ping the server. Anything that can't be
+/// resolved (deleted channel, uncached user, ...) is left as the original
+/// raw token.
+pub async fn render_content(ctx: &Context, guild_id: GuildId, content: &str) -> String {
+    render_tokens(content, |token| render_token(ctx, guild_id, token)).await
+}
+
+async fn render_token(ctx: &Context, guild_id: GuildId, token: String) -> Option<String> {
+    render_token_inner(ctx, guild_id, &token).await
+}
+
+/// Scans `content` for `<...>` tokens, replacing each with whatever `resolve`
+/// returns for it (falling back to the original raw token when `resolve`
+/// returns `None`), then neutralizes `@everyone`/`@here`. Split out from
+/// [`render_content`] so the scanning and neutralization logic can be
+/// exercised with a stub resolver, independent of a real `Context`.
+async fn render_tokens<F, Fut>(content: &str, resolve: F) -> String
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Option<String>>,
+{
+    let mut out = String::with_capacity(content.len());
+    let mut rest = content;
+
+    loop {
+        match rest.find('<') {
+            Some(start) => {
+                out.push_str(&rest[..start]);
+                let after_lt = &rest[start + 1..];
+                match after_lt.find('>') {
+                    Some(end) => {
+                        let token = &after_lt[..end];
+                        match resolve(token.to_string()).await {
+                            Some(rendered) => out.push_str(&rendered),
+                            None => {
+                                out.push('<');
+                                out.push_str(token);
+                                out.push('>');
+                            }
+                        }
+                        rest = &after_lt[end + 1..];
+                    }
+                    None => {
+                        out.push('<');
+                        rest = after_lt;
+                    }
+                }
+            }
+            None => {
+                out.push_str(rest);
+                break;
+            }
+        }
+    }
+
+    neutralize_pings(&out)
+}
+
+/// Replaces `@everyone`/`@here` with a zero-width-space variant so the text
+/// can never re-ping the server, wherever it ends up.
+fn neutralize_pings(content: &str) -> String {
+    content
+        .replace("@everyone", "@\u{200b}everyone")
+        .replace("@here", "@\u{200b}here")
+}
+
+async fn render_token_inner(ctx: &Context, guild_id: GuildId, token: &str) -> Option<String> {
+    if let Some(id) = token.strip_prefix("@&") {
+        let role_id: u64 = id.parse().ok()?;
+        let name = guild_id
+            .to_guild_cached(&ctx.cache)
+            .await?
+            .roles
+            .get(&RoleId(role_id))?
+            .name
+            .clone();
+        return Some(format!("@{}", name));
+    }
+
+    if let Some(id) = token.strip_prefix("@!").or_else(|| token.strip_prefix('@')) {
+        let user_id: u64 = id.parse().ok()?;
+        let member = guild_id.member(ctx, UserId(user_id)).await.ok()?;
+        return Some(format!("@{}", member.display_name()));
+    }
+
+    if let Some(id) = token.strip_prefix('#') {
+        let channel_id: u64 = id.parse().ok()?;
+        let channel = ChannelId(channel_id).to_channel_cached(&ctx.cache).await?;
+        return Some(format!("#{}", channel.guild()?.name));
+    }
+
+    let (_animated, name_and_id) = match token.strip_prefix("a:") {
+        Some(rest) => (true, rest),
+        None => (false, token.strip_prefix(':')?),
+    };
+    let name = name_and_id.split(':').next()?;
+    Some(format!(":{}:", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn unresolvable_tokens_fall_back_to_the_raw_token() {
+        let rendered = render_tokens("hello <@999> and <#999>", |_| async { None }).await;
+        assert_eq!(rendered, "hello <@999> and <#999>");
+    }
+
+    #[tokio::test]
+    async fn resolved_tokens_are_substituted_in_place() {
+        let rendered = render_tokens("hi <@123>, welcome to <#456>", |token| async move {
+            match token.as_str() {
+                "@123" => Some("@alice".to_string()),
+                "#456" => Some("#general".to_string()),
+                _ => None,
+            }
+        })
+        .await;
+        assert_eq!(rendered, "hi @alice, welcome to #general");
+    }
+
+    #[tokio::test]
+    async fn an_unterminated_token_is_left_untouched() {
+        let rendered = render_tokens("broken <@123 mention", |_| async { Some("nope".to_string()) }).await;
+        assert_eq!(rendered, "broken <@123 mention");
+    }
+
+    #[tokio::test]
+    async fn everyone_and_here_are_neutralized_even_after_substitution() {
+        let rendered = render_tokens("@everyone check out <@123>'s post, @here", |_| async {
+            Some("@alice".to_string())
+        })
+        .await;
+        assert_eq!(rendered, "@\u{200b}everyone check out @alice's post, @\u{200b}here");
+    }
+
+    #[test]
+    fn neutralize_pings_only_touches_everyone_and_here() {
+        assert_eq!(neutralize_pings("@everyone"), "@\u{200b}everyone");
+        assert_eq!(neutralize_pings("@here"), "@\u{200b}here");
+        assert_eq!(neutralize_pings("@everyone1 @hereafter"), "@\u{200b}everyone1 @\u{200b}hereafter");
+        assert_eq!(neutralize_pings("hello world"), "hello world");
+    }
+}
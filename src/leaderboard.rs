@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use serenity::model::id::{GuildId, UserId};
+
+use crate::store::Store;
+
+pub const PAGE_SIZE: usize = 10;
+
+/// One author's aggregated standing on the leaderboard.
+pub struct Entry {
+    pub display_name: String,
+    pub total_stars: usize,
+}
+
+/// Sums star counts per author across every message `guild_id` knows about
+/// (watched and promoted alike), highest total first. Entries from other
+/// guilds the bot also serves are excluded.
+pub fn aggregate(store: &Store, guild_id: GuildId) -> Result<Vec<Entry>, String> {
+    let mut totals: HashMap<UserId, (String, usize)> = HashMap::new();
+    for (_, watched) in store.all_watched()? {
+        if watched.guild_id != guild_id {
+            continue;
+        }
+        let author = &watched.message.author;
+        let slot = totals
+            .entry(author.id)
+            .or_insert_with(|| (author.name.clone(), 0));
+        slot.1 += watched.star_count;
+    }
+
+    let mut entries: Vec<Entry> = totals
+        .into_iter()
+        .map(|(_, (display_name, total_stars))| Entry {
+            display_name,
+            total_stars,
+        })
+        .collect();
+    entries.sort_by_key(|e| std::cmp::Reverse(e.total_stars));
+    Ok(entries)
+}
+
+/// Renders `page` (0-indexed) of `entries` as embed description text, plus
+/// whether a previous/next page exists.
+pub fn render_page(entries: &[Entry], page: usize) -> (String, bool, bool) {
+    if entries.is_empty() {
+        return ("No starred messages yet.".to_string(), false, false);
+    }
+
+    let start = page * PAGE_SIZE;
+    let end = (start + PAGE_SIZE).min(entries.len());
+    let lines: Vec<String> = entries[start..end]
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| format!("**{}.** {} — {} \u{2b50}", start + i + 1, entry.display_name, entry.total_stars))
+        .collect();
+
+    let has_prev = page > 0;
+    let has_next = end < entries.len();
+    (lines.join("\n"), has_prev, has_next)
+}
+
+#[cfg(test)]
+mod tests {
+    use serenity::model::id::MessageId;
+
+    use super::*;
+    use crate::{fixtures, WatchedMessage};
+
+    fn put_watched(store: &Store, message_id: u64, author_id: u64, author_name: &str, star_count: usize, guild_id: GuildId) {
+        let watched = WatchedMessage {
+            star_count,
+            message: fixtures::message(message_id, author_id, author_name),
+            guild_id,
+        };
+        store
+            .put_watched(&MessageId(message_id), &watched)
+            .expect("should persist watched message");
+    }
+
+    #[test]
+    fn aggregate_only_counts_the_requesting_guild() {
+        let store = Store::open_temp();
+        let guild_a = GuildId(1);
+        let guild_b = GuildId(2);
+
+        put_watched(&store, 1, 10, "alice", 5, guild_a);
+        put_watched(&store, 2, 11, "bob", 3, guild_a);
+        put_watched(&store, 3, 20, "carol", 100, guild_b);
+
+        let entries = aggregate(&store, guild_a).expect("aggregate should succeed");
+        let names: Vec<&str> = entries.iter().map(|e| e.display_name.as_str()).collect();
+
+        assert_eq!(names, vec!["alice", "bob"]);
+        assert!(entries.iter().all(|e| e.display_name != "carol"));
+    }
+
+    #[test]
+    fn aggregate_sums_stars_per_author_across_messages() {
+        let store = Store::open_temp();
+        let guild = GuildId(1);
+
+        put_watched(&store, 1, 10, "alice", 5, guild);
+        put_watched(&store, 2, 10, "alice", 7, guild);
+
+        let entries = aggregate(&store, guild).expect("aggregate should succeed");
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].total_stars, 12);
+    }
+}
@@ -1,10 +1,21 @@
 use dotenv::dotenv;
 use serenity::{
+    async_trait,
+    builder::CreateEmbed,
     client::Client,
     model::{
         channel::{Channel, Message, Reaction, ReactionType},
         gateway::Ready,
-        id::{ChannelId, EmojiId, GuildId, MessageId},
+        guild::Member,
+        id::{EmojiId, GuildId, MessageId, UserId},
+        interactions::{
+            application_command::{
+                ApplicationCommand, ApplicationCommandInteraction,
+                ApplicationCommandInteractionDataOptionValue, ApplicationCommandOptionType,
+            },
+            message_component::{ButtonStyle, MessageComponentInteraction},
+            Interaction, InteractionApplicationCommandCallbackDataFlags, InteractionResponseType,
+        },
         permissions::Permissions,
     },
     prelude::{Context, EventHandler},
@@ -15,11 +26,29 @@ use std::sync::{Arc, RwLock};
 use std::time::Instant;
 
 use chrono::{offset::Utc, DateTime};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock as AsyncRwLock;
+
+#[cfg(feature = "bridge")]
+mod bridge;
+mod config;
+#[cfg(test)]
+mod fixtures;
+mod format;
+mod leaderboard;
+mod store;
 
-#[derive(Debug)]
+use config::{Defaults, GuildConfig, ResolvedConfig};
+use store::Store;
+
+#[derive(Debug, Serialize, Deserialize)]
 struct WatchedMessage {
-    star_count: usize,
-    message: Message,
+    pub(crate) star_count: usize,
+    pub(crate) message: Message,
+    /// The guild this message was starred in. `Message::guild_id` isn't
+    /// reliably populated on messages fetched over HTTP (see `ready()`),
+    /// so we capture it ourselves at watch time instead of trusting it.
+    pub(crate) guild_id: GuildId,
 }
 
 impl WatchedMessage {
@@ -28,44 +57,72 @@ impl WatchedMessage {
     }
 
     fn on_star_removed(&mut self, reaction_kind: &ReactionKind) {
-        self.star_count -= reaction_kind.power();
+        self.star_count = self.star_count.saturating_sub(reaction_kind.power());
+    }
+
+    fn is_ready_for_pinning(&self, threshold: usize) -> bool {
+        self.star_count >= threshold
     }
 
-    fn is_ready_for_pinning(&self) -> bool {
-        self.star_count >= 10
+    /// Recomputes `star_count` from the reactions Discord currently reports
+    /// on `current`, in case stars were added or removed while the bot was
+    /// offline and missed the write-through to disk.
+    fn reconcile_star_count(&mut self, current: &Message, star_id: &EmojiId, admin_star_id: &EmojiId) {
+        let count = current
+            .reactions
+            .iter()
+            .map(|reaction| match &reaction.reaction_type {
+                ReactionType::Custom { id, .. } if id == admin_star_id => reaction.count as usize * 10,
+                ReactionType::Custom { id, .. } if id == star_id => reaction.count as usize,
+                _ => 0,
+            })
+            .sum();
+        self.star_count = count;
     }
 
-    fn url(&self, guild_id: &GuildId) -> String {
+    pub(crate) fn url(&self, guild_id: &GuildId) -> String {
         format!(
             "https://discordapp.com/channels/{}/{}/{}",
             guild_id, self.message.channel_id, self.message.id
         )
     }
 
-    fn new(
+    async fn new(
         context: &Context,
         reaction: &Reaction,
         kind: &ReactionKind,
+        guild_id: GuildId,
     ) -> Result<WatchedMessage, String> {
         Ok(WatchedMessage {
             star_count: match kind {
                 ReactionKind::AdminStar => 10,
                 ReactionKind::UserStar => 0,
             },
+            guild_id,
             message: reaction
                 .message(&context.http)
+                .await
                 .map_err(|err| format!("Could not retrieve message: {}", err))?,
         })
     }
 }
 
 struct Handler {
-    watched_messages: Arc<RwLock<HashMap<MessageId, WatchedMessage>>>,
-    admin_star_id: EmojiId,
-    star_id: EmojiId,
+    // `tokio::sync::RwLock`, not `std::sync::RwLock`: `reaction_add` has to
+    // hold this across the `.await` on `WatchedMessage::new` so the
+    // "is this message already watched" check and the resulting insert are
+    // one atomic critical section (see `reaction_add` for why).
+    watched_messages: Arc<AsyncRwLock<HashMap<MessageId, WatchedMessage>>>,
     instantiation_time: Instant,
-    starboard_channel: ChannelId,
     starred_message_ids: Arc<RwLock<Vec<MessageId>>>,
+    store: Store,
+    /// Maps an original, starred message to the id of the embed the bot
+    /// posted for it on the starboard, so the embed can be edited in place
+    /// as the star count changes instead of being a one-shot post.
+    promoted_messages: Arc<RwLock<HashMap<MessageId, MessageId>>>,
+    /// Server-wide fallback settings, used until a guild configures its own
+    /// via `/starboard`.
+    defaults: Defaults,
 }
 
 enum ReactionKind {
@@ -83,222 +140,946 @@ impl ReactionKind {
 }
 
 impl Handler {
-    fn new(admin_star_id: u64, star_id: u64, starboard_channel: u64) -> Handler {
-        let watched_messages = Arc::new(RwLock::new(HashMap::with_capacity(32)));
-        let admin_star_id = admin_star_id.into();
-        let star_id = star_id.into();
-        let starboard_channel = starboard_channel.into();
+    fn new(admin_star_id: u64, star_id: u64, starboard_channel: u64, store: Store) -> Handler {
+        let watched_messages = Arc::new(AsyncRwLock::new(HashMap::with_capacity(32)));
         let instantiation_time = Instant::now();
         let starred_message_ids = Arc::new(RwLock::new(Vec::with_capacity(32)));
+        let promoted_messages = Arc::new(RwLock::new(HashMap::with_capacity(32)));
+        let defaults = Defaults {
+            threshold: 10,
+            color: 0xFFCC36,
+            starboard_channel: starboard_channel.into(),
+            star_emoji: star_id.into(),
+            admin_star_emoji: admin_star_id.into(),
+        };
 
         Handler {
             watched_messages,
-            admin_star_id,
-            star_id,
             instantiation_time,
-            starboard_channel,
             starred_message_ids,
+            store,
+            promoted_messages,
+            defaults,
         }
     }
 
-    fn add_message_to_starboard(
-        &self,
+    /// Looks up the calling guild's overrides and merges them with
+    /// `self.defaults`, so the rest of the handler can work with one
+    /// concrete set of settings regardless of whether the guild has
+    /// configured anything.
+    fn resolve_config(&self, guild_id: &GuildId) -> ResolvedConfig {
+        match self.store.get_guild_config(guild_id) {
+            Ok(config) => config.resolve(&self.defaults),
+            Err(err) => {
+                eprintln!("Error loading guild config, using defaults: {}", err);
+                GuildConfig::default().resolve(&self.defaults)
+            }
+        }
+    }
+
+    async fn render_starboard_embed(
         ctx: &Context,
         guild_id: &GuildId,
+        config: &ResolvedConfig,
         watched_message: &WatchedMessage,
-    ) -> Result<(), String> {
-        let star_time: DateTime<Utc> = Utc::now();
+        star_time: &DateTime<Utc>,
+    ) -> CreateEmbed {
         let author = watched_message
             .message
             .author_nick(ctx)
+            .await
             .unwrap_or_else(|| watched_message.message.author.name.clone());
-        self.starboard_channel
+        let description = if !watched_message.message.content.is_empty() {
+            Some(format::render_content(ctx, *guild_id, &watched_message.message.content).await)
+        } else {
+            None
+        };
+
+        let mut e = CreateEmbed::default();
+        let has_message = description.is_some();
+        if let Some(description) = description {
+            e.description(description);
+        }
+        let attachments = &watched_message.message.attachments;
+        if attachments.len() == 1 {
+            if has_message {
+                e.thumbnail(attachments.first().unwrap().url.clone());
+            } else {
+                e.image(attachments.first().unwrap().url.clone());
+            }
+        } else if attachments.len() > 1 {
+            let mut attachments_str = attachments.iter().fold(
+                // discord url length is ~ 77 characters
+                String::with_capacity(77 * attachments.len()),
+                |mut acc, a| {
+                    acc.push_str(&a.url);
+                    acc.push('\n');
+                    acc
+                },
+            );
+            attachments_str.pop();
+            e.description(attachments_str);
+        }
+        e.color(config.color);
+        e.author(|a| {
+            a.name(author);
+            a.url(watched_message.url(guild_id));
+            a.icon_url(watched_message.message.author.face());
+            a
+        });
+        e.footer(|f| f.text(format!("⭐ {}", watched_message.star_count)));
+        e.timestamp(star_time);
+        e
+    }
+
+    async fn add_message_to_starboard(
+        &self,
+        ctx: &Context,
+        guild_id: &GuildId,
+        config: &ResolvedConfig,
+        watched_message: &WatchedMessage,
+    ) -> Result<MessageId, String> {
+        let star_time: DateTime<Utc> = Utc::now();
+        let original_message_id = watched_message.message.id;
+        let embed = Self::render_starboard_embed(ctx, guild_id, config, watched_message, &star_time).await;
+        config
+            .starboard_channel
             .send_message(&ctx.http, |m| {
-                m.embed(|e| {
-                    let has_message = !&watched_message.message.content.is_empty();
-                    if has_message {
-                        e.description(&watched_message.message.content);
-                    }
-                    let attachments = &watched_message.message.attachments;
-                    if attachments.len() == 1 {
-                        if has_message {
-                            e.thumbnail(attachments.get(0).unwrap().url.clone());
-                        } else {
-                            e.image(attachments.get(0).unwrap().url.clone());
-                        }
-                    } else if attachments.len() > 1 {
-                        let mut attachments_str = attachments.iter().fold(
-                            // discord url length is ~ 77 characters
-                            String::with_capacity(77 * attachments.len()),
-                            |mut acc, a| {
-                                acc.push_str(&*a.url);
-                                acc.push('\n');
-                                acc
-                            },
-                        );
-                        attachments_str.pop();
-                        e.description(attachments_str);
-                    }
-                    e.color(0xFFCC36);
-                    e.author(|a| {
-                        a.name(author);
-                        a.url(watched_message.url(&guild_id));
-                        a.icon_url(watched_message.message.author.face());
-                        a
-                    });
-                    e.timestamp(&star_time);
-                    e
-                })
+                m.set_embed(embed)
+                    .components(|c| Self::build_remove_row(c, original_message_id))
             })
+            .await
             .map_err(|err| format!("Could not send message to star board: {}", err))
+            .map(|message| message.id)
+    }
+
+    async fn update_starboard_message(
+        &self,
+        ctx: &Context,
+        guild_id: &GuildId,
+        config: &ResolvedConfig,
+        starboard_message_id: MessageId,
+        watched_message: &WatchedMessage,
+    ) -> Result<(), String> {
+        let star_time: DateTime<Utc> = Utc::now();
+        let original_message_id = watched_message.message.id;
+        let embed = Self::render_starboard_embed(ctx, guild_id, config, watched_message, &star_time).await;
+        config
+            .starboard_channel
+            .edit_message(&ctx.http, starboard_message_id, |m| {
+                m.set_embed(embed)
+                    .components(|c| Self::build_remove_row(c, original_message_id))
+            })
+            .await
+            .map_err(|err| format!("Could not update star board message: {}", err))
             .map(|_| ())
     }
 
-    fn is_valid_reaction(&self, reaction: &Reaction) -> Option<ReactionKind> {
+    /// Keeps a promoted message's starboard embed in sync with its current
+    /// star count, demoting (deleting the post) if it falls back below the
+    /// threshold.
+    async fn sync_starboard_embed(
+        &self,
+        ctx: &Context,
+        guild_id: Option<GuildId>,
+        config: &ResolvedConfig,
+        message_id: &MessageId,
+        watched_message: &WatchedMessage,
+    ) {
+        let guild_id = match guild_id {
+            Some(guild_id) => guild_id,
+            None => return,
+        };
+        let starboard_message_id = match self.promoted_messages.read() {
+            Ok(read_lock) => read_lock.get(message_id).copied(),
+            Err(_) => None,
+        };
+        let starboard_message_id = match starboard_message_id {
+            Some(id) => id,
+            None => return,
+        };
+        if watched_message.is_ready_for_pinning(config.threshold) {
+            if let Err(err) = self
+                .update_starboard_message(ctx, &guild_id, config, starboard_message_id, watched_message)
+                .await
+            {
+                eprintln!("Error updating star board message: {}", err);
+            }
+        } else if let Err(err) = config
+            .starboard_channel
+            .delete_message(&ctx.http, starboard_message_id)
+            .await
+            .map_err(|err| format!("Could not delete star board message: {}", err))
+        {
+            eprintln!("{}", err);
+        } else {
+            if let Ok(mut write_lock) = self.promoted_messages.write() {
+                write_lock.remove(message_id);
+            }
+            if let Err(err) = self.store.remove_promoted(message_id) {
+                eprintln!("Error removing persisted promoted message: {}", err);
+            }
+        }
+    }
+
+    fn is_valid_reaction(&self, reaction: &Reaction, config: &ResolvedConfig) -> Option<ReactionKind> {
         if let ReactionType::Custom {
             id,
             animated: _,
             name: _,
         } = reaction.emoji
         {
-            if id == self.admin_star_id {
+            if id == config.admin_star_emoji {
                 return Some(ReactionKind::AdminStar);
-            } else if id == self.star_id {
+            } else if id == config.star_emoji {
                 return Some(ReactionKind::UserStar);
             }
         }
         None
     }
+
+    /// Whether `user_id` has Administrator in `guild_id`. Shared by the
+    /// admin-star short circuit in `reaction_add` and the `/starboard`
+    /// slash command, which both gate on the same permission.
+    async fn is_administrator(ctx: &Context, guild_id: GuildId, user_id: UserId) -> bool {
+        Self::member_permissions(ctx, guild_id, user_id)
+            .await
+            .map(|perms| perms.contains(Permissions::ADMINISTRATOR))
+            .unwrap_or(false)
+    }
+
+    /// Whether `user_id` can moderate the starboard in `guild_id` (Manage
+    /// Messages or Administrator). Used by the "Remove" button on promoted
+    /// embeds.
+    async fn is_moderator(ctx: &Context, guild_id: GuildId, user_id: UserId) -> bool {
+        Self::member_permissions(ctx, guild_id, user_id)
+            .await
+            .map(|perms| perms.contains(Permissions::MANAGE_MESSAGES) || perms.contains(Permissions::ADMINISTRATOR))
+            .unwrap_or(false)
+    }
+
+    async fn member_permissions(ctx: &Context, guild_id: GuildId, user_id: UserId) -> Option<Permissions> {
+        guild_id.member(ctx, user_id).await.ok()?.permissions(ctx).await.ok()
+    }
+
+    #[cfg(feature = "bridge")]
+    async fn mirror_to_bridges(&self, ctx: &Context, guild_id: &GuildId, watched_message: &WatchedMessage) {
+        match self.store.get_guild_config(guild_id) {
+            Ok(config) if !config.bridge_targets.is_empty() => {
+                bridge::mirror_to_bridges(ctx, guild_id, watched_message, &config.bridge_targets).await;
+            }
+            Ok(_) => {}
+            Err(err) => eprintln!("Error loading guild config for bridge targets: {}", err),
+        }
+    }
 }
 
+#[async_trait]
 impl EventHandler for Handler {
-    fn reaction_remove(&self, _context: Context, reaction: Reaction) {
-        let reaction_kind = self.is_valid_reaction(&reaction);
+    async fn reaction_remove(&self, context: Context, reaction: Reaction) {
+        let guild_id = match reaction.channel(&context).await {
+            Ok(Channel::Guild(channel)) => Some(channel.guild_id),
+            _ => None,
+        };
+        let config = match guild_id {
+            Some(guild_id) => self.resolve_config(&guild_id),
+            None => return,
+        };
+        let reaction_kind = self.is_valid_reaction(&reaction, &config);
         if reaction_kind.is_none() {
             return;
         }
         let reaction_kind = reaction_kind.unwrap();
-        // if we haven't seen this before
-        if let Ok(read_lock) = self.starred_message_ids.read() {
-            if read_lock.contains(&reaction.message_id) {
-                return;
-            }
-        }
-        if let Ok(mut write_lock) = self.watched_messages.write() {
+        // Promoted messages stay in `watched_messages`, so we no longer
+        // short-circuit on `starred_message_ids` here: a star removed after
+        // promotion still needs to update the live embed below.
+        let mut synced = None;
+        {
+            let mut write_lock = self.watched_messages.write().await;
             if let Some(ref mut watched_message) = write_lock.get_mut(&reaction.message_id) {
                 watched_message.on_star_removed(&reaction_kind);
+                if let Err(err) = self.store.put_watched(&reaction.message_id, watched_message) {
+                    eprintln!("Error persisting watched message: {}", err);
+                }
+                synced = Some((watched_message.star_count, watched_message.message.clone(), watched_message.guild_id));
             }
         }
+        if let Some((star_count, message, message_guild_id)) = synced {
+            let watched_message = WatchedMessage { star_count, message, guild_id: message_guild_id };
+            self.sync_starboard_embed(&context, guild_id, &config, &reaction.message_id, &watched_message)
+                .await;
+        }
     }
-    fn reaction_add(&self, context: Context, reaction: Reaction) {
-        let reaction_kind = self.is_valid_reaction(&reaction);
-        if reaction_kind.is_none() {
+    async fn reaction_add(&self, context: Context, reaction: Reaction) {
+        // --- short circuiting ---
+        let guild_id = match reaction.channel(&context).await {
+            Ok(Channel::Guild(channel)) => Some(channel.guild_id),
+            _ => return, // unsupported
+        };
+        let config = self.resolve_config(&guild_id.unwrap());
+
+        if self.store.is_removed(&reaction.message_id).unwrap_or(false) {
+            // A moderator explicitly took this down; don't let new stars
+            // bring it back.
             return;
         }
 
-        let reaction_kind = reaction_kind.unwrap();
-        let guild_id;
-
-        // --- short circuiting ---
-        match reaction.channel(&context) {
-            Ok(Channel::Guild(channel)) => {
-                guild_id = Some(channel.read().guild_id);
-                match reaction_kind {
-                    ReactionKind::AdminStar => {
-                        if let Ok(perms) = guild_id
-                            .unwrap()
-                            .member(&context, reaction.user_id)
-                            .and_then(|m| m.permissions(&context))
-                        {
-                            if !perms.contains(Permissions::ADMINISTRATOR) {
-                                return;
-                            };
-                        }
-                    }
-                    _ => {}
-                }
-            }
-            _ => return, // unsupported
+        let reaction_kind = self.is_valid_reaction(&reaction, &config);
+        if reaction_kind.is_none() {
+            return;
         }
+        let reaction_kind = reaction_kind.unwrap();
 
-        // if we haven't seen this before
-        if let Ok(read_lock) = self.starred_message_ids.read() {
-            if read_lock.contains(&reaction.message_id) {
+        if let ReactionKind::AdminStar = reaction_kind {
+            let user_id = match reaction.user_id {
+                Some(user_id) => user_id,
+                None => return,
+            };
+            if !Self::is_administrator(&context, guild_id.unwrap(), user_id).await {
                 return;
             }
         }
 
-        if let Ok(mut write_lock) = self.watched_messages.write() {
+        // Messages already promoted to the starboard are kept in
+        // `watched_messages` (rather than purged) so further reactions keep
+        // updating the live embed, so we no longer short-circuit on
+        // `starred_message_ids` here.
+        //
+        // The "is this message already watched" check and the resulting
+        // fetch-and-insert have to happen under a single lock acquisition:
+        // serenity spawns a fresh task per reaction_add dispatch, so two
+        // reactions landing on the same never-before-seen message at once
+        // would otherwise both see "not watched yet", both fetch it over
+        // HTTP, and whichever insert() ran last would clobber the other's
+        // star. Holding the write lock across the `.await` below (this is
+        // a `tokio::sync::RwLock`, which is built for exactly that) makes
+        // the check-then-insert atomic instead.
+        {
+            let mut write_lock = self.watched_messages.write().await;
             if let Some(ref mut watched_message) = write_lock.get_mut(&reaction.message_id) {
                 watched_message.on_star_added(&reaction_kind);
             } else {
-                match WatchedMessage::new(&context, &reaction, &reaction_kind) {
+                match WatchedMessage::new(&context, &reaction, &reaction_kind, guild_id.unwrap()).await {
                     Ok(message) => {
                         write_lock.insert(reaction.message_id, message);
                     }
                     Err(err) => eprintln!("Error creating WatchedMessage: {}", err),
                 }
             }
+            if let Some(watched_message) = write_lock.get(&reaction.message_id) {
+                if let Err(err) = self.store.put_watched(&reaction.message_id, watched_message) {
+                    eprintln!("Error persisting watched message: {}", err);
+                }
+            }
+        }
+
+        let already_promoted = self
+            .promoted_messages
+            .read()
+            .map(|read_lock| read_lock.contains_key(&reaction.message_id))
+            .unwrap_or(false);
+
+        if already_promoted {
+            let current = self.watched_messages.read().await.get(&reaction.message_id).map(|m| WatchedMessage {
+                star_count: m.star_count,
+                message: m.message.clone(),
+                guild_id: m.guild_id,
+            });
+            if let Some(watched_message) = current {
+                self.sync_starboard_embed(&context, guild_id, &config, &reaction.message_id, &watched_message)
+                    .await;
+            }
+            return;
         }
-        let mut to_delete = None;
-        if let Ok(read_lock) = self.watched_messages.read() {
-            if let (Some(watched_message), Some(ref guild_id)) =
-                (read_lock.get(&reaction.message_id), guild_id)
+
+        let ready_to_promote = self.watched_messages.read().await.get(&reaction.message_id).and_then(|watched_message| {
+            if watched_message.is_ready_for_pinning(config.threshold) {
+                Some(WatchedMessage {
+                    star_count: watched_message.star_count,
+                    message: watched_message.message.clone(),
+                    guild_id: watched_message.guild_id,
+                })
+            } else {
+                None
+            }
+        });
+
+        let mut newly_promoted = None;
+        if let (Some(watched_message), Some(ref guild_id)) = (ready_to_promote, guild_id) {
+            match self
+                .add_message_to_starboard(&context, guild_id, &config, &watched_message)
+                .await
             {
-                if watched_message.is_ready_for_pinning() {
-                    match self.add_message_to_starboard(&context, guild_id, watched_message) {
-                        Ok(_) => to_delete = Some(watched_message.message.id),
-                        Err(err) => match reaction
-                            .channel_id
-                            .send_message(&context.http, |m| m.content(err))
-                        {
-                            Ok(_) => {}
-                            Err(err) => eprintln!("Error reporting error: {}", err),
-                        },
-                    }
+                Ok(starboard_message_id) => {
+                    #[cfg(feature = "bridge")]
+                    self.mirror_to_bridges(&context, guild_id, &watched_message).await;
+                    newly_promoted = Some((watched_message.message.id, starboard_message_id))
                 }
+                Err(err) => match reaction.channel_id.send_message(&context.http, |m| m.content(err)).await {
+                    Ok(_) => {}
+                    Err(err) => eprintln!("Error reporting error: {}", err),
+                },
             }
         }
-        if let Some(msg_id) = to_delete {
-            if let Ok(mut write_lock) = self.watched_messages.write() {
-                write_lock.remove(&msg_id);
-                if let Ok(mut starred_write_lock) = self.starred_message_ids.write() {
-                    starred_write_lock.push(msg_id);
-                }
+        if let Some((msg_id, starboard_message_id)) = newly_promoted {
+            if let Ok(mut write_lock) = self.promoted_messages.write() {
+                write_lock.insert(msg_id, starboard_message_id);
+            }
+            if let Err(err) = self.store.put_promoted(&msg_id, &starboard_message_id) {
+                eprintln!("Error persisting promoted message: {}", err);
+            }
+            if let Ok(mut starred_write_lock) = self.starred_message_ids.write() {
+                starred_write_lock.push(msg_id);
+            }
+            if let Err(err) = self.store.put_starred(&msg_id) {
+                eprintln!("Error persisting starred message id: {}", err);
             }
         }
     }
 
-    fn ready(&self, context: Context, about_bot: Ready) {
+    async fn ready(&self, context: Context, about_bot: Ready) {
         println!(
             "Bot ready after {}ms, gathering starred messages...",
             Instant::now()
                 .duration_since(self.instantiation_time)
                 .as_millis()
         );
-        if let (Ok(mut write_lock), Ok(messages)) = (
-            self.starred_message_ids.write(),
-            self.starboard_channel
-                .messages(&context.http, |m| m.limit(100)),
-        ) {
-            for message in messages {
-                if message.author.id == about_bot.user.id {
-                    write_lock.push(message.id);
+
+        if let Err(err) = ApplicationCommand::create_global_application_command(&context.http, |command| {
+            command
+                .name("starboard")
+                .description("Configure this server's starboard")
+                .create_option(|option| {
+                    option
+                        .name("threshold")
+                        .description("Stars required before a message is posted to the starboard")
+                        .kind(ApplicationCommandOptionType::SubCommand)
+                        .create_sub_option(|sub| {
+                            sub.name("count")
+                                .description("New star threshold")
+                                .kind(ApplicationCommandOptionType::Integer)
+                                .required(true)
+                        })
+                })
+                .create_option(|option| {
+                    option
+                        .name("color")
+                        .description("Embed color for starboard posts, as a hex code")
+                        .kind(ApplicationCommandOptionType::SubCommand)
+                        .create_sub_option(|sub| {
+                            sub.name("hex")
+                                .description("Hex color, e.g. FFCC36")
+                                .kind(ApplicationCommandOptionType::String)
+                                .required(true)
+                        })
+                })
+                .create_option(|option| {
+                    option
+                        .name("channel")
+                        .description("Channel starred messages get posted to")
+                        .kind(ApplicationCommandOptionType::SubCommand)
+                        .create_sub_option(|sub| {
+                            sub.name("target")
+                                .description("Destination channel")
+                                .kind(ApplicationCommandOptionType::Channel)
+                                .required(true)
+                        })
+                })
+                .create_option(|option| {
+                    option
+                        .name("emoji")
+                        .description("Which emoji count as a regular star")
+                        .kind(ApplicationCommandOptionType::SubCommand)
+                        .create_sub_option(|sub| {
+                            sub.name("star")
+                                .description("Regular star emoji id")
+                                .kind(ApplicationCommandOptionType::String)
+                                .required(true)
+                        })
+                })
+                .create_option(|option| {
+                    option
+                        .name("admin-emoji")
+                        .description("Which emoji count as an admin star (worth 10 regular stars)")
+                        .kind(ApplicationCommandOptionType::SubCommand)
+                        .create_sub_option(|sub| {
+                            sub.name("star")
+                                .description("Admin star emoji id")
+                                .kind(ApplicationCommandOptionType::String)
+                                .required(true)
+                        })
+                })
+                .create_option(|option| {
+                    option
+                        .name("top")
+                        .description("Show the most-starred authors on this server")
+                        .kind(ApplicationCommandOptionType::SubCommand)
+                });
+            #[cfg(feature = "bridge")]
+            command.create_option(|option| {
+                option
+                    .name("bridge")
+                    .description("Mirror starboard posts to an outbound webhook")
+                    .kind(ApplicationCommandOptionType::SubCommand)
+                    .create_sub_option(|sub| {
+                        sub.name("url")
+                            .description("Webhook URL to mirror starboard posts to")
+                            .kind(ApplicationCommandOptionType::String)
+                            .required(true)
+                    })
+            });
+            command
+        })
+        .await
+        {
+            eprintln!("Error registering /starboard command: {}", err);
+        }
+
+        // Restore everything we already know about from the durable store
+        // first, then reconcile with what Discord currently reports so that
+        // stars that changed while the bot was offline aren't lost.
+        match self.store.all_watched() {
+            Ok(entries) => {
+                for (message_id, mut watched_message) in entries {
+                    if let Ok(current) = watched_message
+                        .message
+                        .channel_id
+                        .message(&context.http, message_id)
+                        .await
+                    {
+                        let config = self.resolve_config(&watched_message.guild_id);
+                        watched_message.reconcile_star_count(&current, &config.star_emoji, &config.admin_star_emoji);
+                        watched_message.message = current;
+                    }
+                    self.watched_messages.write().await.insert(message_id, watched_message);
+                }
+                println!("Restored {} watched messages from disk", self.watched_messages.read().await.len());
+            }
+            Err(err) => eprintln!("Error loading watched messages from disk: {}", err),
+        }
+
+        match self.store.all_promoted() {
+            Ok(entries) => {
+                if let Ok(mut write_lock) = self.promoted_messages.write() {
+                    write_lock.extend(entries);
+                }
+                if let Ok(read_lock) = self.promoted_messages.read() {
+                    println!("Restored {} promoted messages from disk", read_lock.len());
                 }
             }
-            println!("Gathered {} already starred messages", write_lock.len());
+            Err(err) => eprintln!("Error loading promoted messages from disk: {}", err),
+        }
+
+        match self.store.all_starred() {
+            Ok(ids) => {
+                if let Ok(mut write_lock) = self.starred_message_ids.write() {
+                    write_lock.extend(ids);
+                }
+            }
+            Err(err) => eprintln!("Error loading starred message ids from disk: {}", err),
+        }
+
+        for guild in &about_bot.guilds {
+            let guild_id = guild.id();
+            let config = self.resolve_config(&guild_id);
+            let messages = match config.starboard_channel.messages(&context.http, |m| m.limit(100)).await {
+                Ok(messages) => messages,
+                Err(_) => continue,
+            };
+            if let Ok(mut write_lock) = self.starred_message_ids.write() {
+                for message in messages {
+                    if message.author.id == about_bot.user.id && !write_lock.contains(&message.id) {
+                        write_lock.push(message.id);
+                        if let Err(err) = self.store.put_starred(&message.id) {
+                            eprintln!("Error persisting starred message id: {}", err);
+                        }
+                    }
+                }
+            }
+        }
+        if let Ok(read_lock) = self.starred_message_ids.read() {
+            println!("Gathered {} already starred messages", read_lock.len());
         }
     }
+
+    async fn interaction_create(&self, context: Context, interaction: Interaction) {
+        let command = match interaction {
+            Interaction::ApplicationCommand(command) => command,
+            Interaction::MessageComponent(component) => {
+                if component.data.custom_id.starts_with("remove_starred:") {
+                    self.handle_remove_button(&context, &component).await;
+                } else {
+                    self.handle_leaderboard_page(&context, &component).await;
+                }
+                return;
+            }
+            _ => return,
+        };
+        if command.data.name != "starboard" {
+            return;
+        }
+        let guild_id = match command.guild_id {
+            Some(guild_id) => guild_id,
+            None => {
+                Self::reply(&context, &command, "This command only works in a server.").await;
+                return;
+            }
+        };
+
+        let subcommand = match command.data.options.first() {
+            Some(subcommand) => subcommand,
+            None => return,
+        };
+
+        // Viewing the leaderboard doesn't touch configuration, so it's open
+        // to anyone; every other subcommand mutates settings and is gated
+        // on Administrator below.
+        if subcommand.name == "top" {
+            self.reply_with_leaderboard(&context, &command, 0).await;
+            return;
+        }
+
+        let is_admin = match command.member.as_ref().map(|m: &Member| m.user.id) {
+            Some(user_id) => Self::is_administrator(&context, guild_id, user_id).await,
+            None => false,
+        };
+        if !is_admin {
+            Self::reply(
+                &context,
+                &command,
+                "You need the Administrator permission to configure the starboard.",
+            )
+            .await;
+            return;
+        }
+
+        let mut config = match self.store.get_guild_config(&guild_id) {
+            Ok(config) => config,
+            Err(err) => {
+                eprintln!("Error loading guild config: {}", err);
+                GuildConfig::default()
+            }
+        };
+
+        let reply = match subcommand.name.as_str() {
+            "threshold" => match subcommand
+                .options
+                .first()
+                .and_then(|opt| opt.resolved.as_ref())
+            {
+                Some(ApplicationCommandInteractionDataOptionValue::Integer(count)) => {
+                    if *count < 1 {
+                        format!("`{}` isn't a valid threshold; it must be at least 1 star.", count)
+                    } else {
+                        config.threshold = Some(*count as usize);
+                        format!("Starboard threshold set to {} stars.", count)
+                    }
+                }
+                _ => "Expected an integer `count`.".to_string(),
+            },
+            "color" => match subcommand
+                .options
+                .first()
+                .and_then(|opt| opt.resolved.as_ref())
+            {
+                Some(ApplicationCommandInteractionDataOptionValue::String(hex)) => {
+                    match u32::from_str_radix(hex.trim_start_matches('#'), 16) {
+                        Ok(color) => {
+                            config.color = Some(color);
+                            format!("Starboard color set to #{:06X}.", color)
+                        }
+                        Err(_) => format!("`{}` isn't a valid hex color.", hex),
+                    }
+                }
+                _ => "Expected a hex color string.".to_string(),
+            },
+            "channel" => match subcommand
+                .options
+                .first()
+                .and_then(|opt| opt.resolved.as_ref())
+            {
+                Some(ApplicationCommandInteractionDataOptionValue::Channel(channel)) => {
+                    config.starboard_channel = Some(channel.id);
+                    format!("Starboard channel set to <#{}>.", channel.id)
+                }
+                _ => "Expected a channel.".to_string(),
+            },
+            "emoji" => match subcommand
+                .options
+                .first()
+                .and_then(|opt| opt.resolved.as_ref())
+            {
+                Some(ApplicationCommandInteractionDataOptionValue::String(id)) => {
+                    match id.parse::<u64>() {
+                        Ok(id) => {
+                            config.star_emoji = Some(EmojiId(id));
+                            format!("Star emoji set to id {}.", id)
+                        }
+                        Err(_) => format!("`{}` isn't a valid emoji id.", id),
+                    }
+                }
+                _ => "Expected an emoji id.".to_string(),
+            },
+            "admin-emoji" => match subcommand
+                .options
+                .first()
+                .and_then(|opt| opt.resolved.as_ref())
+            {
+                Some(ApplicationCommandInteractionDataOptionValue::String(id)) => {
+                    match id.parse::<u64>() {
+                        Ok(id) => {
+                            config.admin_star_emoji = Some(EmojiId(id));
+                            format!("Admin star emoji set to id {}.", id)
+                        }
+                        Err(_) => format!("`{}` isn't a valid emoji id.", id),
+                    }
+                }
+                _ => "Expected an emoji id.".to_string(),
+            },
+            #[cfg(feature = "bridge")]
+            "bridge" => match subcommand
+                .options
+                .first()
+                .and_then(|opt| opt.resolved.as_ref())
+            {
+                Some(ApplicationCommandInteractionDataOptionValue::String(url)) => {
+                    if bridge::is_discord_webhook_url(url) {
+                        config.bridge_targets.push(bridge::BridgeTarget::Webhook { url: url.clone() });
+                        format!("Starboard posts will now also be mirrored to {}.", url)
+                    } else {
+                        format!("`{}` isn't a Discord webhook URL.", url)
+                    }
+                }
+                _ => "Expected a webhook URL.".to_string(),
+            },
+            other => format!("Unknown subcommand `{}`.", other),
+        };
+
+        if let Err(err) = self.store.put_guild_config(&guild_id, &config) {
+            eprintln!("Error persisting guild config: {}", err);
+        }
+        Self::reply(&context, &command, reply).await;
+    }
 }
 
-fn main() -> Result<(), String> {
+impl Handler {
+    async fn reply(ctx: &Context, command: &ApplicationCommandInteraction, content: impl ToString) {
+        if let Err(err) = command
+            .create_interaction_response(&ctx.http, |response| {
+                response
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|data| {
+                        data.content(content.to_string())
+                            .flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+                    })
+            })
+            .await
+        {
+            eprintln!("Error responding to /starboard: {}", err);
+        }
+    }
+
+    /// Attaches the moderator-only "Remove" button every starboard embed
+    /// gets, keyed to the original (starred) message so the handler knows
+    /// what to suppress.
+    fn build_remove_row(
+        components: &mut serenity::builder::CreateComponents,
+        original_message_id: MessageId,
+    ) -> &mut serenity::builder::CreateComponents {
+        components.create_action_row(|row| {
+            row.create_button(|b| {
+                b.custom_id(format!("remove_starred:{}", original_message_id))
+                    .label("Remove")
+                    .style(ButtonStyle::Danger)
+            })
+        })
+    }
+
+    async fn handle_remove_button(&self, ctx: &Context, component: &MessageComponentInteraction) {
+        let guild_id = match component.guild_id {
+            Some(guild_id) => guild_id,
+            None => return,
+        };
+        let is_moderator = match component.member.as_ref().map(|m| m.user.id) {
+            Some(user_id) => Self::is_moderator(ctx, guild_id, user_id).await,
+            None => false,
+        };
+        if !is_moderator {
+            if let Err(err) = component
+                .create_interaction_response(&ctx.http, |response| {
+                    response
+                        .kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|data| {
+                            data.content("You need Manage Messages to remove a starboard post.")
+                                .flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+                        })
+                })
+                .await
+            {
+                eprintln!("Error responding to remove button: {}", err);
+            }
+            return;
+        }
+
+        let original_message_id = match component
+            .data
+            .custom_id
+            .strip_prefix("remove_starred:")
+            .and_then(|id| id.parse::<u64>().ok())
+        {
+            Some(id) => MessageId(id),
+            None => return,
+        };
+
+        if let Err(err) = component
+            .message
+            .channel_id
+            .delete_message(&ctx.http, component.message.id)
+            .await
+        {
+            eprintln!("Error deleting starboard message: {}", err);
+        }
+        if let Ok(mut write_lock) = self.promoted_messages.write() {
+            write_lock.remove(&original_message_id);
+        }
+        if let Err(err) = self.store.remove_promoted(&original_message_id) {
+            eprintln!("Error removing persisted promoted message: {}", err);
+        }
+        self.watched_messages.write().await.remove(&original_message_id);
+        if let Err(err) = self.store.remove_watched(&original_message_id) {
+            eprintln!("Error removing persisted watched message: {}", err);
+        }
+        if let Err(err) = self.store.mark_removed(&original_message_id) {
+            eprintln!("Error marking message as removed: {}", err);
+        }
+
+        if let Err(err) = component
+            .create_interaction_response(&ctx.http, |response| {
+                response
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|data| {
+                        data.content("Removed from the starboard.")
+                            .flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+                    })
+            })
+            .await
+        {
+            eprintln!("Error responding to remove button: {}", err);
+        }
+    }
+
+    fn build_leaderboard_row(
+        components: &mut serenity::builder::CreateComponents,
+        page: usize,
+        has_prev: bool,
+        has_next: bool,
+    ) -> &mut serenity::builder::CreateComponents {
+        components.create_action_row(|row| {
+            row.create_button(|b| {
+                b.custom_id(format!("lb_prev:{}", page))
+                    .label("Previous")
+                    .style(ButtonStyle::Secondary)
+                    .disabled(!has_prev)
+            })
+            .create_button(|b| {
+                b.custom_id(format!("lb_next:{}", page))
+                    .label("Next")
+                    .style(ButtonStyle::Secondary)
+                    .disabled(!has_next)
+            })
+        })
+    }
+
+    async fn reply_with_leaderboard(&self, ctx: &Context, command: &ApplicationCommandInteraction, page: usize) {
+        let guild_id = match command.guild_id {
+            Some(guild_id) => guild_id,
+            None => return,
+        };
+        let entries = match leaderboard::aggregate(&self.store, guild_id) {
+            Ok(entries) => entries,
+            Err(err) => {
+                eprintln!("Error aggregating leaderboard: {}", err);
+                Self::reply(ctx, command, "Could not load the leaderboard.").await;
+                return;
+            }
+        };
+        let (description, has_prev, has_next) = leaderboard::render_page(&entries, page);
+
+        if let Err(err) = command
+            .create_interaction_response(&ctx.http, |response| {
+                response
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|data| {
+                        data.create_embed(|e| e.title("\u{2b50} Starboard leaderboard").description(description))
+                            .components(|c| Self::build_leaderboard_row(c, page, has_prev, has_next))
+                    })
+            })
+            .await
+        {
+            eprintln!("Error responding to /starboard top: {}", err);
+        }
+    }
+
+    async fn handle_leaderboard_page(&self, ctx: &Context, component: &MessageComponentInteraction) {
+        let (direction, page) = match component.data.custom_id.split_once(':') {
+            Some((direction, page)) => (direction, page),
+            None => return,
+        };
+        let page: usize = match page.parse() {
+            Ok(page) => page,
+            Err(_) => return,
+        };
+        let page = match direction {
+            "lb_prev" => page.saturating_sub(1),
+            "lb_next" => page + 1,
+            _ => return,
+        };
+        let guild_id = match component.guild_id {
+            Some(guild_id) => guild_id,
+            None => return,
+        };
+
+        let entries = match leaderboard::aggregate(&self.store, guild_id) {
+            Ok(entries) => entries,
+            Err(err) => {
+                eprintln!("Error aggregating leaderboard: {}", err);
+                return;
+            }
+        };
+        let (description, has_prev, has_next) = leaderboard::render_page(&entries, page);
+
+        if let Err(err) = component
+            .create_interaction_response(&ctx.http, |response| {
+                response
+                    .kind(InteractionResponseType::UpdateMessage)
+                    .interaction_response_data(|data| {
+                        data.create_embed(|e| e.title("\u{2b50} Starboard leaderboard").description(description))
+                            .components(|c| Self::build_leaderboard_row(c, page, has_prev, has_next))
+                    })
+            })
+            .await
+        {
+            eprintln!("Error updating leaderboard page: {}", err);
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), String> {
     dotenv().map_err(|e| format!("Error loading dotenv: {}", e))?;
 
+    let store = Store::open(std::env::var("STARS_DB_PATH").unwrap_or_else(|_| "stars.db".to_string()))?;
+
+    let token = std::env::var("DISCORD_TOKEN").map_err(|err| format!("Error getting discord token: {}", err))?;
+    let application_id = std::env::var("DISCORD_APPLICATION_ID")
+        .map_err(|err| format!("Error getting discord application id: {}", err))?
+        .parse::<u64>()
+        .map_err(|err| format!("Error parsing discord application id as u64: {}", err))?;
+
     // Login with a bot token from the environment
-    let mut client = Client::new(
-        &std::env::var("DISCORD_TOKEN")
-            .map_err(|err| format!("Error getting discord token: {}", err))?,
-        Handler::new(
+    let mut client = Client::builder(&token)
+        .application_id(application_id)
+        .event_handler(Handler::new(
             std::env::var("ADMIN_STAR_EMOJI_ID")
                 .map_err(|err| format!("Error getting admin emoji star id: {}", err))?
                 .parse::<u64>()
@@ -311,12 +1092,14 @@ fn main() -> Result<(), String> {
                 .map_err(|err| format!("Error getting starboard channel id: {}", err))?
                 .parse::<u64>()
                 .map_err(|err| format!("Error parsing starbound channel id as u64: {}", err))?,
-        ),
-    )
-    .map_err(|err| format!("Error instantiating client: {}", err))?;
+            store,
+        ))
+        .await
+        .map_err(|err| format!("Error instantiating client: {}", err))?;
 
     // start listening for events by starting a single shard
     client
         .start()
+        .await
         .map_err(|err| format!("Error starting server: {}", err))
 }
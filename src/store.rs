@@ -0,0 +1,274 @@
+use std::path::Path;
+
+use serenity::model::id::{GuildId, MessageId};
+
+use crate::config::GuildConfig;
+use crate::WatchedMessage;
+
+/// Durable storage for in-flight star counts, the set of messages that have
+/// already been promoted to the starboard, and per-guild configuration,
+/// backed by `sled` so the bot doesn't lose state across restarts.
+pub struct Store {
+    watched: sled::Tree,
+    starred: sled::Tree,
+    guild_configs: sled::Tree,
+    removed: sled::Tree,
+    promoted: sled::Tree,
+}
+
+impl Store {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Store, String> {
+        let db = sled::open(path).map_err(|err| format!("Could not open sled db: {}", err))?;
+        Self::from_db(db)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn open_temp() -> Store {
+        let db = sled::Config::new()
+            .temporary(true)
+            .open()
+            .expect("could not open temporary sled db");
+        Self::from_db(db).expect("could not open trees in temporary sled db")
+    }
+
+    fn from_db(db: sled::Db) -> Result<Store, String> {
+        let watched = db
+            .open_tree("watched_messages")
+            .map_err(|err| format!("Could not open watched_messages tree: {}", err))?;
+        let starred = db
+            .open_tree("starred_message_ids")
+            .map_err(|err| format!("Could not open starred_message_ids tree: {}", err))?;
+        let guild_configs = db
+            .open_tree("guild_configs")
+            .map_err(|err| format!("Could not open guild_configs tree: {}", err))?;
+        let removed = db
+            .open_tree("removed_message_ids")
+            .map_err(|err| format!("Could not open removed_message_ids tree: {}", err))?;
+        let promoted = db
+            .open_tree("promoted_message_ids")
+            .map_err(|err| format!("Could not open promoted_message_ids tree: {}", err))?;
+        Ok(Store {
+            watched,
+            starred,
+            guild_configs,
+            removed,
+            promoted,
+        })
+    }
+
+    fn key(message_id: &MessageId) -> [u8; 8] {
+        message_id.0.to_le_bytes()
+    }
+
+    fn guild_key(guild_id: &GuildId) -> [u8; 8] {
+        guild_id.0.to_le_bytes()
+    }
+
+    // `WatchedMessage` embeds a full serenity `Message`, which carries a
+    // `serde_json::Value` nonce field; `Value`'s `Deserialize` impl always
+    // calls `deserialize_any`, which bincode can't support. Use JSON for
+    // this tree instead so a round trip actually works.
+    pub fn put_watched(&self, message_id: &MessageId, watched: &WatchedMessage) -> Result<(), String> {
+        let bytes = serde_json::to_vec(watched)
+            .map_err(|err| format!("Could not serialize watched message: {}", err))?;
+        self.watched
+            .insert(Self::key(message_id), bytes)
+            .map_err(|err| format!("Could not persist watched message: {}", err))?;
+        Ok(())
+    }
+
+    pub fn remove_watched(&self, message_id: &MessageId) -> Result<(), String> {
+        self.watched
+            .remove(Self::key(message_id))
+            .map_err(|err| format!("Could not remove watched message: {}", err))?;
+        Ok(())
+    }
+
+    pub fn put_starred(&self, message_id: &MessageId) -> Result<(), String> {
+        self.starred
+            .insert(Self::key(message_id), &[])
+            .map_err(|err| format!("Could not persist starred message id: {}", err))?;
+        Ok(())
+    }
+
+    pub fn all_watched(&self) -> Result<Vec<(MessageId, WatchedMessage)>, String> {
+        self.watched
+            .iter()
+            .map(|entry| {
+                let (key, value) =
+                    entry.map_err(|err| format!("Could not read watched message: {}", err))?;
+                let mut bytes = [0u8; 8];
+                bytes.copy_from_slice(&key);
+                let message_id = MessageId(u64::from_le_bytes(bytes));
+                let watched: WatchedMessage = serde_json::from_slice(&value)
+                    .map_err(|err| format!("Could not deserialize watched message: {}", err))?;
+                Ok((message_id, watched))
+            })
+            .collect()
+    }
+
+    pub fn all_starred(&self) -> Result<Vec<MessageId>, String> {
+        self.starred
+            .iter()
+            .keys()
+            .map(|key| {
+                let key = key.map_err(|err| format!("Could not read starred message id: {}", err))?;
+                let mut bytes = [0u8; 8];
+                bytes.copy_from_slice(&key);
+                Ok(MessageId(u64::from_le_bytes(bytes)))
+            })
+            .collect()
+    }
+
+    pub fn get_guild_config(&self, guild_id: &GuildId) -> Result<GuildConfig, String> {
+        match self
+            .guild_configs
+            .get(Self::guild_key(guild_id))
+            .map_err(|err| format!("Could not read guild config: {}", err))?
+        {
+            Some(bytes) => bincode::deserialize(&bytes)
+                .map_err(|err| format!("Could not deserialize guild config: {}", err)),
+            None => Ok(GuildConfig::default()),
+        }
+    }
+
+    pub fn put_guild_config(&self, guild_id: &GuildId, config: &GuildConfig) -> Result<(), String> {
+        let bytes = bincode::serialize(config)
+            .map_err(|err| format!("Could not serialize guild config: {}", err))?;
+        self.guild_configs
+            .insert(Self::guild_key(guild_id), bytes)
+            .map_err(|err| format!("Could not persist guild config: {}", err))?;
+        Ok(())
+    }
+
+    /// Marks a message as moderator-removed from the starboard so it won't
+    /// be re-promoted if it picks up fresh stars later.
+    pub fn mark_removed(&self, message_id: &MessageId) -> Result<(), String> {
+        self.removed
+            .insert(Self::key(message_id), &[])
+            .map_err(|err| format!("Could not mark message as removed: {}", err))?;
+        Ok(())
+    }
+
+    pub fn is_removed(&self, message_id: &MessageId) -> Result<bool, String> {
+        self.removed
+            .contains_key(Self::key(message_id))
+            .map_err(|err| format!("Could not check removed message ids: {}", err))
+    }
+
+    /// Records that `original_id` has been promoted to the starboard as
+    /// `starboard_id`, so the mapping survives a restart and a message
+    /// already on the starboard isn't posted there a second time.
+    pub fn put_promoted(&self, original_id: &MessageId, starboard_id: &MessageId) -> Result<(), String> {
+        self.promoted
+            .insert(Self::key(original_id), &Self::key(starboard_id))
+            .map_err(|err| format!("Could not persist promoted message: {}", err))?;
+        Ok(())
+    }
+
+    pub fn remove_promoted(&self, original_id: &MessageId) -> Result<(), String> {
+        self.promoted
+            .remove(Self::key(original_id))
+            .map_err(|err| format!("Could not remove promoted message: {}", err))?;
+        Ok(())
+    }
+
+    pub fn all_promoted(&self) -> Result<Vec<(MessageId, MessageId)>, String> {
+        self.promoted
+            .iter()
+            .map(|entry| {
+                let (key, value) =
+                    entry.map_err(|err| format!("Could not read promoted message: {}", err))?;
+                let mut original_bytes = [0u8; 8];
+                original_bytes.copy_from_slice(&key);
+                let mut starboard_bytes = [0u8; 8];
+                starboard_bytes.copy_from_slice(&value);
+                Ok((
+                    MessageId(u64::from_le_bytes(original_bytes)),
+                    MessageId(u64::from_le_bytes(starboard_bytes)),
+                ))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures, WatchedMessage};
+
+    #[test]
+    fn watched_messages_round_trip() {
+        let store = Store::open_temp();
+        let message_id = MessageId(1);
+        let watched = WatchedMessage {
+            star_count: 3,
+            message: fixtures::message(1, 10, "alice"),
+            guild_id: GuildId(100),
+        };
+
+        store.put_watched(&message_id, &watched).expect("should persist");
+        let entries = store.all_watched().expect("should load");
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].0, message_id);
+        assert_eq!(entries[0].1.star_count, 3);
+        assert_eq!(entries[0].1.guild_id, GuildId(100));
+
+        store.remove_watched(&message_id).expect("should remove");
+        assert!(store.all_watched().expect("should load").is_empty());
+    }
+
+    #[test]
+    fn starred_message_ids_round_trip() {
+        let store = Store::open_temp();
+        let message_id = MessageId(42);
+
+        store.put_starred(&message_id).expect("should persist");
+
+        assert_eq!(store.all_starred().expect("should load"), vec![message_id]);
+    }
+
+    #[test]
+    fn promoted_messages_round_trip() {
+        let store = Store::open_temp();
+        let original_id = MessageId(1);
+        let starboard_id = MessageId(2);
+
+        store.put_promoted(&original_id, &starboard_id).expect("should persist");
+        assert_eq!(store.all_promoted().expect("should load"), vec![(original_id, starboard_id)]);
+
+        store.remove_promoted(&original_id).expect("should remove");
+        assert!(store.all_promoted().expect("should load").is_empty());
+    }
+
+    #[test]
+    fn removed_messages_round_trip() {
+        let store = Store::open_temp();
+        let message_id = MessageId(1);
+
+        assert!(!store.is_removed(&message_id).expect("should check"));
+
+        store.mark_removed(&message_id).expect("should persist");
+
+        assert!(store.is_removed(&message_id).expect("should check"));
+    }
+
+    #[test]
+    fn guild_config_round_trip_with_defaults_fallback() {
+        let store = Store::open_temp();
+        let guild_id = GuildId(100);
+
+        // No config saved yet: falls back to defaults rather than erroring.
+        assert_eq!(store.get_guild_config(&guild_id).expect("should load").threshold, None);
+
+        let config = GuildConfig {
+            threshold: Some(5),
+            ..GuildConfig::default()
+        };
+        store.put_guild_config(&guild_id, &config).expect("should persist");
+
+        let loaded = store.get_guild_config(&guild_id).expect("should load");
+        assert_eq!(loaded.threshold, Some(5));
+    }
+}